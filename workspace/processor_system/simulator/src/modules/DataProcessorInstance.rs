@@ -1,41 +1,62 @@
-use crate::simulator::Simulator;
+use crate::simulator::{ModuleId, SimFault, Simulator};
 use sim_runtime::num_bigint::{BigInt, BigUint};
 use sim_runtime::*;
 use std::ffi::c_void;
 
 // Elaborating module DataProcessorInstance
-pub fn DataProcessorInstance(sim: &mut Simulator) -> bool {
+pub fn DataProcessorInstance(sim: &mut Simulator) -> Result<bool, SimFault> {
   let data_in_valid = { !sim.DataProcessorInstance_data_in.is_empty() };
   let enable_valid = { !sim.DataProcessorInstance_enable.is_empty() };
   let data_in_and_enable_valid =
     { ValueCastTo::<bool>::cast(&data_in_valid) & ValueCastTo::<bool>::cast(&enable_valid) };
   // @/mnt/d/Tomato_Fish/豫文化课/新时代/大二秋/系统/assassyn/./assassyn_example.py:49
   if !data_in_and_enable_valid {
-    return false;
+    return Err(SimFault::FifoUnderflow {
+      fifo: if data_in_valid {
+        "DataProcessorInstance_enable"
+      } else {
+        "DataProcessorInstance_data_in"
+      },
+      site: "assassyn_example.py:49",
+    });
   };
   let data_in = {
     {
-      let stamp = sim.stamp - sim.stamp % 100 + 50;
+      let stamp = sim
+        .element_clock("DataProcessorInstance_data_in")
+        .this_cycle_write_stamp(sim.stamp);
       sim
         .DataProcessorInstance_data_in
         .pop
         .push(FIFOPop::new(stamp, "DataProcessorInstance"));
       match sim.DataProcessorInstance_data_in.payload.front() {
         Some(value) => value.clone(),
-        None => return false,
+        None => {
+          return Err(SimFault::FifoUnderflow {
+            fifo: "DataProcessorInstance_data_in",
+            site: "assassyn_example.py:49",
+          })
+        }
       }
     }
   };
   let enable = {
     {
-      let stamp = sim.stamp - sim.stamp % 100 + 50;
+      let stamp = sim
+        .element_clock("DataProcessorInstance_enable")
+        .this_cycle_write_stamp(sim.stamp);
       sim
         .DataProcessorInstance_enable
         .pop
         .push(FIFOPop::new(stamp, "DataProcessorInstance"));
       match sim.DataProcessorInstance_enable.payload.front() {
         Some(value) => value.clone(),
-        None => return false,
+        None => {
+          return Err(SimFault::FifoUnderflow {
+            fifo: "DataProcessorInstance_enable",
+            site: "assassyn_example.py:49",
+          })
+        }
       }
     }
   };
@@ -44,18 +65,20 @@ pub fn DataProcessorInstance(sim: &mut Simulator) -> bool {
     let result = { ValueCastTo::<u64>::cast(&data_in) * ValueCastTo::<u64>::cast(&2u32) };
     // @/mnt/d/Tomato_Fish/豫文化课/新时代/大二秋/系统/assassyn/./assassyn_example.py:60
     {
-      let stamp = sim.stamp - sim.stamp % 100 + 50;
+      let stamp = sim
+        .element_clock("processed_data")
+        .this_cycle_write_stamp(sim.stamp);
       let write = ArrayWrite::new(stamp, false as usize, result.clone(), "DataProcessorInstance");
       sim.processed_data.write(0, write);
     };
     // @/mnt/d/Tomato_Fish/豫文化课/新时代/大二秋/系统/assassyn/./assassyn_example.py:63
-    print!("@line:{:<5} {:<10}: [DataProcessorInstance]\t", line!(), cyclize(sim.stamp));
+    print!("@line:{:<5} {:<10}: [DataProcessorInstance]\t", line!(), sim.clock_of(ModuleId::DataProcessorInstance).cyclize(sim.stamp));
     println!("Processing: {} -> {}", data_in, result,);
   }
   let processed_data_rd = { sim.processed_data.payload[false as usize].clone() };
   // @/mnt/d/Tomato_Fish/豫文化课/新时代/大二秋/系统/assassyn/./assassyn_example.py:66
-  print!("@line:{:<5} {:<10}: [DataProcessorInstance]\t", line!(), cyclize(sim.stamp));
+  print!("@line:{:<5} {:<10}: [DataProcessorInstance]\t", line!(), sim.clock_of(ModuleId::DataProcessorInstance).cyclize(sim.stamp));
   println!("Processed data available: {}", processed_data_rd,);
 
-  true
+  Ok(true)
 }
@@ -2,49 +2,493 @@ use crate::modules;
 use sim_runtime::num_bigint::{BigInt, BigUint};
 use sim_runtime::rand::seq::SliceRandom;
 use sim_runtime::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+/// Default simulation horizon: the last module activation lands at `100 * 100`
+/// and its register tick half a cycle later.
+const DEFAULT_HORIZON: usize = 10_050;
+
+/// Identifies a module so it can be addressed by the event scheduler.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ModuleId {
+  CounterInstance,
+  DataProcessorInstance,
+  Driver,
+}
+
+/// A hazard raised by a module entry point.
+///
+/// The scheduler decides policy per variant: [`SimFault::FifoUnderflow`] is
+/// benign back-pressure and merely re-queues the module on the next cycle
+/// edge, while [`SimFault::Deadlock`] is fatal — it aborts with a diagnostic
+/// unless a user-registered handler claims it first. This design's FIFOs are
+/// unbounded (`FIFO::new()`) and its every `Array` access is a compile-time
+/// `0` into a size-1 array, so a push-capacity or index-bounds hazard can
+/// never actually occur here; those variants (and the narrowing-cast one,
+/// since every generated cast in this design is same-width or widening) are
+/// omitted rather than kept as taxonomy nothing ever constructs.
+#[derive(Clone, Debug)]
+pub enum SimFault {
+  /// A pop from an empty FIFO — the consumer simply has nothing to do yet.
+  FifoUnderflow {
+    fifo: &'static str,
+    site: &'static str,
+  },
+  /// Every module scheduled in a cycle stalled on back-pressure and nothing
+  /// advanced; carries the stalled module set.
+  Deadlock {
+    modules: Vec<ModuleId>,
+  },
+}
+
+impl std::fmt::Display for SimFault {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SimFault::FifoUnderflow { fifo, site } => {
+        write!(f, "FIFO underflow on `{}` ({})", fifo, site)
+      }
+      SimFault::Deadlock { modules } => write!(f, "deadlock among modules {:?}", modules),
+    }
+  }
+}
+
+/// Every module in this design, in declaration order.
+const MODULES: &[ModuleId] = &[
+  ModuleId::CounterInstance,
+  ModuleId::DataProcessorInstance,
+  ModuleId::Driver,
+];
+
+/// Intra-cycle `producer -> consumer` dependencies: a `FIFOPush` at `stamp + 50`
+/// into another module's FIFO, or an array read that observes a same-cycle
+/// write. Edges that only take effect across a register `tick` boundary (a
+/// `stamp + 100` activation, a self-recurrent register) are sequential and are
+/// intentionally omitted so they do not constrain the single-pass order.
+///
+/// `Driver -> DataProcessorInstance` is one such sequential edge: the push
+/// into `DataProcessorInstance_data_in` commits at the register tick, and
+/// `Driver` explicitly schedules `DataProcessorInstance` at `next_cycle_stamp`
+/// — the following cycle, not this one — so it is excluded here too.
+const INTRA_CYCLE_EDGES: &[(ModuleId, ModuleId)] = &[];
+
+/// Modules whose activation is scheduled explicitly from within another
+/// module's body (a `sim.schedule(.., Event::Module(..))` call, e.g. `Driver`
+/// driving `DataProcessorInstance` a cycle later) rather than by `run()`
+/// itself. Every module *not* listed here has nothing upstream of it and is a
+/// dispatch root: `run()` seeds it with a periodic self-schedule on its own
+/// clock domain. `CounterInstance` has no producer in this design either —
+/// it is a second, unrelated root alongside `Driver` — so it is seeded too.
+const DRIVEN_MODULES: &[ModuleId] = &[ModuleId::DataProcessorInstance];
+
+/// A scheduled happening in the global event queue.
+///
+/// `Module` activations sort before `TickRegisters` at the same stamp so that
+/// the combinational logic for a cycle runs before its registers commit.
+/// `TickRegisters` carries the domain whose edge fired it, so only the
+/// arrays/FIFOs assigned to that domain commit — a domain's elements tick on
+/// its own edges, not on every other domain's edges too.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Event {
+  Module(ModuleId),
+  DramResponse,
+  TickRegisters(ClockDomain),
+}
+
+/// A clock domain: a periodic rising edge every `period` stamp units, with
+/// register writes committing `write_phase` units after the edge (the classic
+/// `+50` mid-cycle commit of a 100-unit period). Centralising the period and
+/// phase here keeps the magic numbers out of generated module code and lets
+/// modules, arrays and FIFOs run on independent clocks.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ClockDomain {
+  pub period: usize,
+  pub write_phase: usize,
+}
+
+/// The default single clock: a 100-unit period committing registers at `+50`.
+pub const DEFAULT_CLOCK: ClockDomain = ClockDomain {
+  period: 100,
+  write_phase: 50,
+};
+
+impl ClockDomain {
+  pub const fn new(period: usize, write_phase: usize) -> Self {
+    ClockDomain { period, write_phase }
+  }
+
+  /// The cycle index containing `stamp`.
+  pub fn cyclize(&self, stamp: usize) -> usize {
+    stamp / self.period
+  }
+
+  /// The stamp at which a register write issued during `stamp`'s cycle commits
+  /// — the rising edge of the current cycle plus the write phase.
+  pub fn this_cycle_write_stamp(&self, stamp: usize) -> usize {
+    stamp - stamp % self.period + self.write_phase
+  }
+
+  /// The rising edge of the cycle after the one containing `stamp`.
+  pub fn next_cycle_stamp(&self, stamp: usize) -> usize {
+    stamp - stamp % self.period + self.period
+  }
+}
+
+/// Fixed DRAM response latency, in stamp units.
+const DRAM_LATENCY: usize = 100;
+/// Page size of the sparse backing store: 4 KiB chunks allocate lazily.
+const DRAM_PAGE_SIZE: usize = 1 << 12;
+
+/// A DRAM access in flight, awaiting its response edge.
+struct DramRequest {
+  id: i64,
+  response_stamp: usize,
+  target: ModuleId,
+  write: bool,
+  value: u64,
+}
+
+/// Latency-accurate external memory with a sparse, page-chunked backing store.
+///
+/// Pages materialize only when touched (read or written), so a multi-gigabyte
+/// address space costs nothing until accessed. Accesses return a request id immediately and their
+/// response is delivered `DRAM_LATENCY` stamps later via a scheduled
+/// [`Event::DramResponse`], draining [`Dram::complete`].
+pub struct Dram {
+  pages: HashMap<u64, Box<[u8]>>,
+  pending: Vec<DramRequest>,
+  next_id: i64,
+}
+
+impl Dram {
+  pub fn new() -> Self {
+    Dram {
+      pages: HashMap::new(),
+      pending: Vec::new(),
+      next_id: 0,
+    }
+  }
+
+  fn page_mut(&mut self, page: u64) -> &mut [u8] {
+    self
+      .pages
+      .entry(page)
+      .or_insert_with(|| vec![0u8; DRAM_PAGE_SIZE].into_boxed_slice())
+  }
+
+  /// Load a little-endian `u64` word from `addr`, spanning at most one page.
+  fn load(&mut self, addr: u64) -> u64 {
+    let page = addr / DRAM_PAGE_SIZE as u64;
+    let off = (addr % DRAM_PAGE_SIZE as u64) as usize;
+    let chunk = self.page_mut(page);
+    let mut bytes = [0u8; 8];
+    let n = (DRAM_PAGE_SIZE - off).min(8);
+    bytes[..n].copy_from_slice(&chunk[off..off + n]);
+    u64::from_le_bytes(bytes)
+  }
+
+  /// Store a little-endian `u64` word at `addr`, spanning at most one page.
+  fn store(&mut self, addr: u64, value: u64) {
+    let page = addr / DRAM_PAGE_SIZE as u64;
+    let off = (addr % DRAM_PAGE_SIZE as u64) as usize;
+    let chunk = self.page_mut(page);
+    let bytes = value.to_le_bytes();
+    let n = (DRAM_PAGE_SIZE - off).min(8);
+    chunk[off..off + n].copy_from_slice(&bytes[..n]);
+  }
+
+  /// Issue a read, returning the request id immediately. The loaded value is
+  /// captured now and handed back when the response edge arrives.
+  fn issue_read(&mut self, addr: u64, issue_stamp: usize, target: ModuleId) -> i64 {
+    let id = self.next_id;
+    self.next_id += 1;
+    let value = self.load(addr);
+    self.pending.push(DramRequest {
+      id,
+      response_stamp: issue_stamp + DRAM_LATENCY,
+      target,
+      write: false,
+      value,
+    });
+    id
+  }
+
+  /// Issue a write, returning the request id immediately. The store is applied
+  /// at once; the response edge only retires the request.
+  fn issue_write(&mut self, addr: u64, value: u64, issue_stamp: usize, target: ModuleId) -> i64 {
+    let id = self.next_id;
+    self.next_id += 1;
+    self.store(addr, value);
+    self.pending.push(DramRequest {
+      id,
+      response_stamp: issue_stamp + DRAM_LATENCY,
+      target,
+      write: true,
+      value,
+    });
+    id
+  }
+
+  /// Drain every request whose response stamp has arrived, returning the
+  /// read responses to deliver as `(target, value, id)`.
+  fn complete(&mut self, stamp: usize) -> Vec<(ModuleId, u64, i64)> {
+    let mut done = Vec::new();
+    let mut still_pending = Vec::with_capacity(self.pending.len());
+    for req in self.pending.drain(..) {
+      if req.response_stamp <= stamp {
+        if !req.write {
+          done.push((req.target, req.value, req.id));
+        }
+      } else {
+        still_pending.push(req);
+      }
+    }
+    self.pending = still_pending;
+    done
+  }
+}
+
+/// A watchpoint on a named `Array`, fired when its payload changes via an
+/// `ArrayWrite` that commits on the register tick. `predicate`, when present,
+/// gates the hit on the freshly written value.
+pub struct ArrayWatch {
+  pub name: &'static str,
+  pub predicate: Option<Box<dyn Fn(i64) -> bool>>,
+}
+
+/// A watchpoint on a named `FIFO`, fired when its occupancy changes via a push
+/// or pop that commits on the register tick.
+pub struct FifoWatch {
+  pub name: &'static str,
+}
+
+/// Interactive inspection hooked into the scheduler.
+///
+/// Modelled after the moa debugger: a `trace_only` flag for non-halting
+/// logging, breakpoints that fire as a module is about to be dispatched, and
+/// watchpoints on array/FIFO traffic. `repeat` skips that many matching stops
+/// before halting so embedders can issue repeat-count commands. The default is
+/// fully disabled, so `simulate()` behaves exactly as if no debugger existed.
+pub struct Debugger {
+  pub enabled: bool,
+  pub trace_only: bool,
+  pub step: bool,
+  pub breakpoints: Vec<ModuleId>,
+  pub array_watch: Vec<ArrayWatch>,
+  pub fifo_watch: Vec<FifoWatch>,
+  pub repeat: usize,
+}
+
+impl Debugger {
+  pub fn new() -> Self {
+    Debugger {
+      enabled: false,
+      trace_only: false,
+      step: false,
+      breakpoints: Vec::new(),
+      array_watch: Vec::new(),
+      fifo_watch: Vec::new(),
+      repeat: 0,
+    }
+  }
+
+  /// Read one command from stdin and update the stop state. Empty input (or a
+  /// closed stream, as in a non-interactive run) resumes; `s` single-steps,
+  /// stopping again before the next module dispatch, whether at this stamp or
+  /// a later one (stamps that only tick registers are not stops); `q` detaches
+  /// the debugger; a bare integer sets a repeat count that skips that many
+  /// subsequent hits.
+  fn prompt(&mut self) {
+    use std::io::Write;
+    print!("(dbg) ");
+    let _ = std::io::stdout().flush();
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+      self.step = false;
+      return;
+    }
+    match line.trim() {
+      "" | "c" | "continue" => self.step = false,
+      "s" | "step" => self.step = true,
+      "q" | "quit" => self.enabled = false,
+      other => {
+        if let Ok(n) = other.parse::<usize>() {
+          self.repeat = n;
+          self.step = false;
+        }
+      }
+    }
+  }
+}
+
+/// Occupancy snapshot used to detect array/FIFO watchpoint hits across a stamp.
+struct WatchSnapshot {
+  arrays: Vec<(&'static str, i64)>,
+  fifos: Vec<(&'static str, usize)>,
+}
+
 pub struct Simulator {
   pub stamp: usize,
   pub request_stamp_map_table: HashMap<i64, usize>,
+  pub event_queue: BinaryHeap<Reverse<(usize, usize, Event)>>,
+  pub debugger: Debugger,
+  pub dram: Dram,
+  /// Per-cycle dispatch order of the combinational modules, produced by
+  /// [`analyze_schedule`] so a consumer never runs before its producer.
+  pub dispatch_order: Vec<ModuleId>,
+  /// Modules fed purely combinationally within a cycle (non-zero in-degree in
+  /// the dependency graph); the complement of the scheduling roots.
+  pub downstreams: Vec<ModuleId>,
   pub cnt: Array<u32>,
   pub processed_data: Array<u32>,
   pub cycle_cnt: Array<u32>,
   pub test_data: Array<u32>,
   pub CounterInstance_triggered: bool,
-  pub CounterInstance_event: VecDeque<usize>,
   pub DataProcessorInstance_triggered: bool,
-  pub DataProcessorInstance_event: VecDeque<usize>,
   pub DataProcessorInstance_data_in: FIFO<u32>,
   pub DataProcessorInstance_enable: FIFO<bool>,
   pub Driver_triggered: bool,
-  pub Driver_event: VecDeque<usize>,
+  /// The design-wide default clock. Modules, arrays and FIFOs fall back to it
+  /// unless assigned an explicit domain below.
+  pub clock: ClockDomain,
+  /// Per-module clock overrides, keyed by module.
+  pub module_clocks: HashMap<ModuleId, ClockDomain>,
+  /// Per-array and per-FIFO clock overrides, keyed by the state element's name.
+  pub element_clocks: HashMap<&'static str, ClockDomain>,
+  /// Optional policy hook for fatal faults. Returning `true` means the handler
+  /// absorbed the fault and the run continues; `false` (or no handler) lets the
+  /// scheduler abort with a diagnostic.
+  pub fault_handler: Option<fn(&SimFault) -> bool>,
+  /// Modules that stalled on back-pressure during the stamp currently being
+  /// dispatched, and whether anything advanced. Reset at each populated stamp
+  /// and inspected afterwards for deadlock.
+  stalled_modules: Vec<ModuleId>,
+  made_progress: bool,
+}
+
+/// Order the modules for single-pass evaluation via Kahn's algorithm over
+/// [`INTRA_CYCLE_EDGES`], returning the dispatch order alongside the set of
+/// combinational downstream modules. `Err` carries the modules still caught in
+/// a purely combinational (zero-delay) loop.
+fn analyze_schedule() -> Result<(Vec<ModuleId>, Vec<ModuleId>), Vec<ModuleId>> {
+  let mut in_degree: HashMap<ModuleId, usize> = MODULES.iter().map(|&m| (m, 0)).collect();
+  for &(_, consumer) in INTRA_CYCLE_EDGES {
+    *in_degree.get_mut(&consumer).unwrap() += 1;
+  }
+  let downstreams: Vec<ModuleId> = MODULES
+    .iter()
+    .copied()
+    .filter(|m| in_degree[m] > 0)
+    .collect();
+
+  let mut remaining = in_degree.clone();
+  let mut ready: VecDeque<ModuleId> =
+    MODULES.iter().copied().filter(|m| remaining[m] == 0).collect();
+  let mut order = Vec::with_capacity(MODULES.len());
+  while let Some(module) = ready.pop_front() {
+    order.push(module);
+    for &(producer, consumer) in INTRA_CYCLE_EDGES {
+      if producer == module {
+        let deg = remaining.get_mut(&consumer).unwrap();
+        *deg -= 1;
+        if *deg == 0 {
+          ready.push_back(consumer);
+        }
+      }
+    }
+  }
+
+  if order.len() != MODULES.len() {
+    let cycle: Vec<ModuleId> = MODULES
+      .iter()
+      .copied()
+      .filter(|m| !order.contains(m))
+      .collect();
+    return Err(cycle);
+  }
+  Ok((order, downstreams))
 }
 
 impl Simulator {
   pub fn new() -> Self {
+    let (dispatch_order, downstreams) = analyze_schedule()
+      .unwrap_or_else(|cycle| panic!("combinational loop detected among modules: {:?}", cycle));
     Simulator {
       stamp: 0,
       request_stamp_map_table: HashMap::new(),
+      event_queue: BinaryHeap::new(),
+      debugger: Debugger::new(),
+      dram: Dram::new(),
+      dispatch_order,
+      downstreams,
       cnt: Array::new_with_ports(1, 1),
       processed_data: Array::new_with_ports(1, 1),
       cycle_cnt: Array::new_with_ports(1, 1),
       test_data: Array::new_with_ports(1, 1),
       CounterInstance_triggered: false,
-      CounterInstance_event: VecDeque::new(),
       DataProcessorInstance_triggered: false,
-      DataProcessorInstance_event: VecDeque::new(),
       DataProcessorInstance_data_in: FIFO::new(),
       DataProcessorInstance_enable: FIFO::new(),
       Driver_triggered: false,
-      Driver_event: VecDeque::new(),
+      clock: DEFAULT_CLOCK,
+      module_clocks: HashMap::new(),
+      element_clocks: HashMap::new(),
+      fault_handler: None,
+      stalled_modules: Vec::new(),
+      made_progress: false,
+    }
+  }
+
+  /// Tie-break rank within a single stamp: modules follow the topological
+  /// dispatch order, and register ticks always come last.
+  fn event_rank(&self, event: Event) -> usize {
+    match event {
+      Event::Module(module) => self
+        .dispatch_order
+        .iter()
+        .position(|&m| m == module)
+        .unwrap_or(usize::MAX - 2),
+      Event::DramResponse => usize::MAX - 1,
+      Event::TickRegisters(_) => usize::MAX,
     }
   }
 
-  fn event_valid(&self, event: &VecDeque<usize>) -> bool {
-    event.front().map_or(false, |x| *x <= self.stamp)
+  /// Insert `event` into the global event queue to fire at `stamp`.
+  pub fn schedule(&mut self, stamp: usize, event: Event) {
+    let rank = self.event_rank(event);
+    self.event_queue.push(Reverse((stamp, rank, event)));
+  }
+
+  /// The clock domain a module runs on (its override, else the default).
+  pub fn clock_of(&self, module: ModuleId) -> ClockDomain {
+    self.module_clocks.get(&module).copied().unwrap_or(self.clock)
+  }
+
+  /// The clock domain a named array or FIFO commits on (its override, else the
+  /// default). A FIFO's pushes and pops commit on its own domain's edges, which
+  /// are the consuming module's edges only when both share a domain.
+  pub fn element_clock(&self, name: &'static str) -> ClockDomain {
+    self.element_clocks.get(name).copied().unwrap_or(self.clock)
+  }
+
+  /// The distinct clock domains in play, so the scheduler can step each one's
+  /// edges independently (the general multi-domain case reduces to a single
+  /// entry here when every element shares the default clock).
+  fn distinct_domains(&self) -> Vec<ClockDomain> {
+    let mut domains = vec![self.clock];
+    for d in self
+      .module_clocks
+      .values()
+      .chain(self.element_clocks.values())
+    {
+      if !domains.contains(d) {
+        domains.push(*d);
+      }
+    }
+    domains
   }
 
   pub fn reset_downstream(&mut self) {
@@ -53,95 +497,303 @@ impl Simulator {
     self.Driver_triggered = false;
   }
 
-  pub fn tick_registers(&mut self) {
-    self.cnt.tick(self.stamp);
-    self.processed_data.tick(self.stamp);
-    self.cycle_cnt.tick(self.stamp);
-    self.test_data.tick(self.stamp);
-    self.DataProcessorInstance_data_in.tick(self.stamp);
-    self.DataProcessorInstance_enable.tick(self.stamp);
+  /// Commit every array/FIFO whose assigned clock domain is `domain` — the
+  /// elements belonging to any other domain sit untouched until their own
+  /// edge fires this again with their domain.
+  pub fn tick_registers(&mut self, domain: ClockDomain) {
+    if self.element_clock("cnt") == domain {
+      self.cnt.tick(self.stamp);
+    }
+    if self.element_clock("processed_data") == domain {
+      self.processed_data.tick(self.stamp);
+    }
+    if self.element_clock("cycle_cnt") == domain {
+      self.cycle_cnt.tick(self.stamp);
+    }
+    if self.element_clock("test_data") == domain {
+      self.test_data.tick(self.stamp);
+    }
+    if self.element_clock("DataProcessorInstance_data_in") == domain {
+      self.DataProcessorInstance_data_in.tick(self.stamp);
+    }
+    if self.element_clock("DataProcessorInstance_enable") == domain {
+      self.DataProcessorInstance_enable.tick(self.stamp);
+    }
   }
 
-  pub fn reset_dram(&mut self) {}
+  pub fn reset_dram(&mut self) {
+    self.dram = Dram::new();
+    self.request_stamp_map_table.clear();
+  }
 
-  fn simulate_CounterInstance(&mut self) {
-    if self.event_valid(&self.CounterInstance_event) {
-      let succ = modules::CounterInstance::CounterInstance(self);
-      if succ {
-        self.CounterInstance_event.pop_front();
-      } else {
-      }
-      self.CounterInstance_triggered = succ;
-    } // close event condition
-  } // close function
+  /// Issue a DRAM read on behalf of `target`, recording the issue stamp in
+  /// `request_stamp_map_table` and scheduling the response edge. Returns the
+  /// request id the module can correlate against the delivered response.
+  pub fn dram_read(&mut self, addr: u64, target: ModuleId) -> i64 {
+    let id = self.dram.issue_read(addr, self.stamp, target);
+    self.request_stamp_map_table.insert(id, self.stamp);
+    self.schedule(self.stamp + DRAM_LATENCY, Event::DramResponse);
+    id
+  }
 
-  fn simulate_DataProcessorInstance(&mut self) {
-    if self.event_valid(&self.DataProcessorInstance_event) {
-      let succ = modules::DataProcessorInstance::DataProcessorInstance(self);
-      if succ {
-        self.DataProcessorInstance_event.pop_front();
-      } else {
-      }
-      self.DataProcessorInstance_triggered = succ;
-    } // close event condition
-  } // close function
+  /// Issue a DRAM write on behalf of `target`; see [`Simulator::dram_read`].
+  pub fn dram_write(&mut self, addr: u64, value: u64, target: ModuleId) -> i64 {
+    let id = self.dram.issue_write(addr, value, self.stamp, target);
+    self.request_stamp_map_table.insert(id, self.stamp);
+    self.schedule(self.stamp + DRAM_LATENCY, Event::DramResponse);
+    id
+  }
 
-  fn simulate_Driver(&mut self) {
-    if self.event_valid(&self.Driver_event) {
-      let succ = modules::Driver::Driver(self);
-      if succ {
-        self.Driver_event.pop_front();
-      } else {
+  /// Complete every DRAM request whose response stamp has arrived, delivering
+  /// read results into the requesting module's input FIFO. Only
+  /// `DataProcessorInstance` has one; results for other modules are discarded.
+  fn tick_dram(&mut self) {
+    for (target, value, id) in self.dram.complete(self.stamp) {
+      self.request_stamp_map_table.remove(&id);
+      match target {
+        ModuleId::DataProcessorInstance => {
+          self
+            .DataProcessorInstance_data_in
+            .push
+            .push(FIFOPush::new(self.stamp, value as u32, "Dram"));
+        }
+        ModuleId::CounterInstance | ModuleId::Driver => {}
       }
-      self.Driver_triggered = succ;
-    } // close event condition
-  } // close function
-}
+    }
+  }
 
-pub fn simulate() {
-  let mut sim = Simulator::new();
-  let simulators: Vec<fn(&mut Simulator)> = vec![
-    Simulator::simulate_CounterInstance,
-    Simulator::simulate_DataProcessorInstance,
-    Simulator::simulate_Driver,
-  ];
-  let downstreams: Vec<fn(&mut Simulator)> = vec![];
+  /// Dump the current stamp/cycle, every `Array` payload, and the contents of
+  /// every `FIFO` — the state view shown whenever the debugger halts.
+  fn dump_state(&self) {
+    println!("-- debugger stop @ stamp {} (cycle {})", self.stamp, self.clock.cyclize(self.stamp));
+    println!("   cnt            = {:?}", self.cnt.payload);
+    println!("   processed_data = {:?}", self.processed_data.payload);
+    println!("   cycle_cnt      = {:?}", self.cycle_cnt.payload);
+    println!("   test_data      = {:?}", self.test_data.payload);
+    println!(
+      "   DataProcessorInstance_data_in = {:?}",
+      self.DataProcessorInstance_data_in.payload
+    );
+    println!(
+      "   DataProcessorInstance_enable  = {:?}",
+      self.DataProcessorInstance_enable.payload
+    );
+  }
+
+  /// Breakpoint / trace hook, invoked just before `module` is dispatched.
+  fn debug_on_module(&mut self, module: ModuleId) {
+    if !self.debugger.enabled {
+      return;
+    }
+    if self.debugger.trace_only {
+      println!("[trace] stamp {} dispatch {:?}", self.stamp, module);
+      return;
+    }
+    let hit = self.debugger.step || self.debugger.breakpoints.contains(&module);
+    if !hit {
+      return;
+    }
+    if self.debugger.repeat > 0 {
+      self.debugger.repeat -= 1;
+      return;
+    }
+    println!("[break] module {:?} about to dispatch", module);
+    self.dump_state();
+    self.debugger.prompt();
+  }
 
-  for i in 1..=100 {
-    sim.Driver_event.push_back(i * 100);
+  /// Capture array values and FIFO occupancies so watchpoint changes can be
+  /// detected across a stamp's events.
+  fn watch_snapshot(&self) -> WatchSnapshot {
+    WatchSnapshot {
+      arrays: vec![
+        ("cnt", ValueCastTo::<i64>::cast(&self.cnt.payload[0])),
+        ("processed_data", ValueCastTo::<i64>::cast(&self.processed_data.payload[0])),
+        ("cycle_cnt", ValueCastTo::<i64>::cast(&self.cycle_cnt.payload[0])),
+        ("test_data", ValueCastTo::<i64>::cast(&self.test_data.payload[0])),
+      ],
+      fifos: vec![
+        ("DataProcessorInstance_data_in", self.DataProcessorInstance_data_in.payload.len()),
+        ("DataProcessorInstance_enable", self.DataProcessorInstance_enable.payload.len()),
+      ],
+    }
   }
-  let mut idle_count = 0;
-  for i in 1..=100 {
-    sim.stamp = i * 100;
-    sim.reset_downstream();
 
-    for simulate in simulators.iter() {
-      simulate(&mut sim);
+  /// Watchpoint / trace hook, invoked after a stamp's events have fired. Arrays
+  /// and FIFOs whose state changed are matched against the registered watches.
+  fn debug_on_watch(&mut self, before: &WatchSnapshot, after: &WatchSnapshot) {
+    if !self.debugger.enabled {
+      return;
     }
+    let mut hit = false;
+    for watch in &self.debugger.array_watch {
+      if let (Some(b), Some(a)) = (
+        before.arrays.iter().find(|(n, _)| *n == watch.name),
+        after.arrays.iter().find(|(n, _)| *n == watch.name),
+      ) {
+        if b.1 != a.1 && watch.predicate.as_ref().map_or(true, |p| p(a.1)) {
+          if self.debugger.trace_only {
+            println!("[trace] array {} = {}", watch.name, a.1);
+          } else {
+            println!("[watch] array {} -> {}", watch.name, a.1);
+            hit = true;
+          }
+        }
+      }
+    }
+    for watch in &self.debugger.fifo_watch {
+      if let (Some(b), Some(a)) = (
+        before.fifos.iter().find(|(n, _)| *n == watch.name),
+        after.fifos.iter().find(|(n, _)| *n == watch.name),
+      ) {
+        if b.1 != a.1 {
+          if self.debugger.trace_only {
+            println!("[trace] fifo {} len {} -> {}", watch.name, b.1, a.1);
+          } else {
+            println!("[watch] fifo {} len {} -> {}", watch.name, b.1, a.1);
+            hit = true;
+          }
+        }
+      }
+    }
+    if hit && !self.debugger.trace_only {
+      if self.debugger.repeat > 0 {
+        self.debugger.repeat -= 1;
+        return;
+      }
+      self.dump_state();
+      self.debugger.prompt();
+    }
+  }
 
-    for simulate in downstreams.iter() {
-      simulate(&mut sim);
+  fn set_triggered(&mut self, module: ModuleId, value: bool) {
+    match module {
+      ModuleId::CounterInstance => self.CounterInstance_triggered = value,
+      ModuleId::DataProcessorInstance => self.DataProcessorInstance_triggered = value,
+      ModuleId::Driver => self.Driver_triggered = value,
     }
+  }
 
-    let any_module_triggered =
-      sim.CounterInstance_triggered || sim.DataProcessorInstance_triggered || sim.Driver_triggered;
+  /// Run a single scheduled event. Module activations that cannot make progress
+  /// (back-pressure, e.g. an empty input FIFO) are re-queued on the next cycle
+  /// edge rather than spinning the whole time wheel.
+  fn dispatch(&mut self, event: Event) {
+    match event {
+      Event::TickRegisters(domain) => {
+        self.tick_registers(domain);
+      }
+      Event::DramResponse => {
+        self.tick_dram();
+      }
+      Event::Module(module) => {
+        self.debug_on_module(module);
+        let result = match module {
+          ModuleId::CounterInstance => modules::CounterInstance::CounterInstance(self),
+          ModuleId::DataProcessorInstance => {
+            modules::DataProcessorInstance::DataProcessorInstance(self)
+          }
+          ModuleId::Driver => modules::Driver::Driver(self),
+        };
+        match result {
+          Ok(succ) => {
+            self.set_triggered(module, succ);
+            if succ {
+              self.made_progress = true;
+            } else {
+              self.requeue_stalled(module);
+            }
+          }
+          // Back-pressure is benign: the consumer just had no input this cycle.
+          Err(SimFault::FifoUnderflow { .. }) => {
+            self.set_triggered(module, false);
+            self.requeue_stalled(module);
+          }
+          Err(fault) => self.handle_fault(fault),
+        }
+      }
+    }
+  }
 
-    // Handle idle threshold
-    if !any_module_triggered {
-      idle_count += 1;
-      if idle_count >= 100 {
-        println!("Simulation stopped due to reaching idle threshold of 100");
-        break;
+  /// Re-queue a module that made no progress onto the next cycle edge and
+  /// record it for deadlock detection.
+  fn requeue_stalled(&mut self, module: ModuleId) {
+    let retry = self.clock_of(module).next_cycle_stamp(self.stamp);
+    self.schedule(retry, Event::Module(module));
+    self.stalled_modules.push(module);
+  }
+
+  /// Apply the configured policy for a fatal fault: offer it to the registered
+  /// handler, and abort with a diagnostic if the handler declines (or none is
+  /// set). The diagnostic carries the offending stamp alongside the source site
+  /// baked into the fault.
+  fn handle_fault(&self, fault: SimFault) {
+    if let Some(handler) = self.fault_handler {
+      if handler(&fault) {
+        return;
       }
-    } else {
-      idle_count = 0;
     }
+    panic!("simulation fault at stamp {}: {}", self.stamp, fault);
+  }
 
-    sim.stamp += 50;
-    sim.tick_registers();
-    sim.reset_dram();
-    unsafe {
-      // Tick all DRAM memory interfaces
+  /// Event-driven core. Pops the earliest event, advances `stamp` to it, and
+  /// dispatches every event sharing that stamp before moving on, so idle time
+  /// is skipped entirely. `on_stamp` is invoked once per populated stamp after
+  /// its events have fired; returning `false` stops the run early.
+  pub fn run(&mut self, horizon: Option<usize>, mut on_stamp: impl FnMut(&Simulator) -> bool) {
+    self.reset_dram();
+    let limit = horizon.unwrap_or(DEFAULT_HORIZON);
+    // Every module not in DRIVEN_MODULES is a dispatch root: it has nothing
+    // upstream of it, so it fires on each rising edge of its own domain
+    // instead of waiting to be scheduled by another module.
+    for &root in MODULES.iter().filter(|m| !DRIVEN_MODULES.contains(m)) {
+      let domain = self.clock_of(root);
+      let mut edge = domain.period;
+      while edge <= limit {
+        self.schedule(edge, Event::Module(root));
+        edge += domain.period;
+      }
+    }
+    // Registers commit at every domain's own write-phase edge, and
+    // `tick_registers` only commits the elements assigned to the domain that
+    // fired: each distinct domain is stepped independently at its own period
+    // rather than on a shared, merged schedule.
+    for domain in self.distinct_domains() {
+      let mut edge = domain.period + domain.write_phase;
+      while edge <= limit {
+        self.schedule(edge, Event::TickRegisters(domain));
+        edge += domain.period;
+      }
+    }
+
+    while let Some(Reverse((stamp, _, _))) = self.event_queue.peek().copied() {
+      if horizon.map_or(false, |h| stamp > h) {
+        break;
+      }
+      self.stamp = stamp;
+      self.reset_downstream();
+      self.stalled_modules.clear();
+      self.made_progress = false;
+      let before = self.watch_snapshot();
+      while matches!(self.event_queue.peek(), Some(Reverse((s, _, _))) if *s == stamp) {
+        let Reverse((_, _, event)) = self.event_queue.pop().unwrap();
+        self.dispatch(event);
+      }
+      // A cycle in which every dispatched module stalled on back-pressure and
+      // nothing advanced cannot make progress on its own — a deadlock.
+      if !self.made_progress && !self.stalled_modules.is_empty() {
+        let modules = self.stalled_modules.clone();
+        self.handle_fault(SimFault::Deadlock { modules });
+      }
+      let after = self.watch_snapshot();
+      self.debug_on_watch(&before, &after);
+      if !on_stamp(self) {
+        break;
+      }
     }
   }
 }
+
+pub fn simulate() {
+  let mut sim = Simulator::new();
+  sim.run(Some(DEFAULT_HORIZON), |_| true);
+}
@@ -2,91 +2,489 @@ use crate::modules;
 use sim_runtime::num_bigint::{BigInt, BigUint};
 use sim_runtime::rand::seq::SliceRandom;
 use sim_runtime::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+/// Default simulation horizon: the last module activation lands at `100 * 100`
+/// and its register tick half a cycle later.
+const DEFAULT_HORIZON: usize = 10_050;
+
+/// Identifies a module so it can be addressed by the event scheduler.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ModuleId {
+  SimpleCounterInstance,
+}
+
+/// Every module in this design, in declaration order.
+const MODULES: &[ModuleId] = &[ModuleId::SimpleCounterInstance];
+
+/// Intra-cycle `producer -> consumer` dependencies: a `FIFOPush` at `stamp + 50`
+/// into another module's FIFO, or an array read that observes a same-cycle
+/// write. Edges that only take effect across a register `tick` boundary (a
+/// `stamp + 100` activation, a self-recurrent register) are sequential and are
+/// intentionally omitted so they do not constrain the single-pass order.
+const INTRA_CYCLE_EDGES: &[(ModuleId, ModuleId)] = &[];
+
+/// Modules whose activation is scheduled explicitly from within another
+/// module's body rather than by `run()` itself. `SimpleCounterInstance` has
+/// no producer, so it is this design's sole dispatch root and `run()` seeds
+/// it with a periodic self-schedule; there is nothing here to drive it
+/// instead.
+const DRIVEN_MODULES: &[ModuleId] = &[];
+
+/// A scheduled happening in the global event queue.
+///
+/// `Module` activations sort before `TickRegisters` at the same stamp so that
+/// the combinational logic for a cycle runs before its registers commit.
+/// `TickRegisters` carries the domain whose edge fired it, so only the
+/// arrays/FIFOs assigned to that domain commit — a domain's elements tick on
+/// its own edges, not on every other domain's edges too.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Event {
+  Module(ModuleId),
+  TickRegisters(ClockDomain),
+}
+
+/// A clock domain: a periodic rising edge every `period` stamp units, with
+/// register writes committing `write_phase` units after the edge (the classic
+/// `+50` mid-cycle commit of a 100-unit period). Centralising the period and
+/// phase here keeps the magic numbers out of generated module code and lets
+/// modules, arrays and FIFOs run on independent clocks.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ClockDomain {
+  pub period: usize,
+  pub write_phase: usize,
+}
+
+/// The default single clock: a 100-unit period committing registers at `+50`.
+pub const DEFAULT_CLOCK: ClockDomain = ClockDomain {
+  period: 100,
+  write_phase: 50,
+};
+
+impl ClockDomain {
+  pub const fn new(period: usize, write_phase: usize) -> Self {
+    ClockDomain { period, write_phase }
+  }
+
+  /// The cycle index containing `stamp`.
+  pub fn cyclize(&self, stamp: usize) -> usize {
+    stamp / self.period
+  }
+
+  /// The stamp at which a register write issued during `stamp`'s cycle commits
+  /// — the rising edge of the current cycle plus the write phase.
+  pub fn this_cycle_write_stamp(&self, stamp: usize) -> usize {
+    stamp - stamp % self.period + self.write_phase
+  }
+
+  /// The rising edge of the cycle after the one containing `stamp`.
+  pub fn next_cycle_stamp(&self, stamp: usize) -> usize {
+    stamp - stamp % self.period + self.period
+  }
+}
+
+/// A watchpoint on a named `Array`, fired when its payload changes via an
+/// `ArrayWrite` that commits on the register tick. `predicate`, when present,
+/// gates the hit on the freshly written value.
+pub struct ArrayWatch {
+  pub name: &'static str,
+  pub predicate: Option<Box<dyn Fn(i64) -> bool>>,
+}
+
+/// A watchpoint on a named `FIFO`, fired when its occupancy changes via a push
+/// or pop that commits on the register tick.
+pub struct FifoWatch {
+  pub name: &'static str,
+}
+
+/// Interactive inspection hooked into the scheduler.
+///
+/// Modelled after the moa debugger: a `trace_only` flag for non-halting
+/// logging, breakpoints that fire as a module is about to be dispatched, and
+/// watchpoints on array/FIFO traffic. `repeat` skips that many matching stops
+/// before halting so embedders can issue repeat-count commands. The default is
+/// fully disabled, so `simulate()` behaves exactly as if no debugger existed.
+pub struct Debugger {
+  pub enabled: bool,
+  pub trace_only: bool,
+  pub step: bool,
+  pub breakpoints: Vec<ModuleId>,
+  pub array_watch: Vec<ArrayWatch>,
+  pub fifo_watch: Vec<FifoWatch>,
+  pub repeat: usize,
+}
+
+impl Debugger {
+  pub fn new() -> Self {
+    Debugger {
+      enabled: false,
+      trace_only: false,
+      step: false,
+      breakpoints: Vec::new(),
+      array_watch: Vec::new(),
+      fifo_watch: Vec::new(),
+      repeat: 0,
+    }
+  }
+
+  /// Read one command from stdin and update the stop state. Empty input (or a
+  /// closed stream, as in a non-interactive run) resumes; `s` single-steps,
+  /// stopping again before the next module dispatch, whether at this stamp or
+  /// a later one (stamps that only tick registers are not stops); `q` detaches
+  /// the debugger; a bare integer sets a repeat count that skips that many
+  /// subsequent hits.
+  fn prompt(&mut self) {
+    use std::io::Write;
+    print!("(dbg) ");
+    let _ = std::io::stdout().flush();
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+      self.step = false;
+      return;
+    }
+    match line.trim() {
+      "" | "c" | "continue" => self.step = false,
+      "s" | "step" => self.step = true,
+      "q" | "quit" => self.enabled = false,
+      other => {
+        if let Ok(n) = other.parse::<usize>() {
+          self.repeat = n;
+          self.step = false;
+        }
+      }
+    }
+  }
+}
+
+/// Occupancy snapshot used to detect array/FIFO watchpoint hits across a stamp.
+struct WatchSnapshot {
+  arrays: Vec<(&'static str, i64)>,
+  fifos: Vec<(&'static str, usize)>,
+}
+
 pub struct Simulator {
   pub stamp: usize,
   pub request_stamp_map_table: HashMap<i64, usize>,
+  pub event_queue: BinaryHeap<Reverse<(usize, usize, Event)>>,
+  pub debugger: Debugger,
+  /// Per-cycle dispatch order of the combinational modules, produced by
+  /// [`analyze_schedule`] so a consumer never runs before its producer.
+  pub dispatch_order: Vec<ModuleId>,
+  /// Modules fed purely combinationally within a cycle (non-zero in-degree in
+  /// the dependency graph); the complement of the scheduling roots.
+  pub downstreams: Vec<ModuleId>,
   pub cnt: Array<u32>,
   pub SimpleCounterInstance_triggered: bool,
-  pub SimpleCounterInstance_event: VecDeque<usize>,
+  /// The design-wide default clock. Modules, arrays and FIFOs fall back to it
+  /// unless assigned an explicit domain below.
+  pub clock: ClockDomain,
+  /// Per-module clock overrides, keyed by module.
+  pub module_clocks: HashMap<ModuleId, ClockDomain>,
+  /// Per-array and per-FIFO clock overrides, keyed by the state element's name.
+  pub element_clocks: HashMap<&'static str, ClockDomain>,
+}
+
+/// Order the modules for single-pass evaluation via Kahn's algorithm over
+/// [`INTRA_CYCLE_EDGES`], returning the dispatch order alongside the set of
+/// combinational downstream modules. `Err` carries the modules still caught in
+/// a purely combinational (zero-delay) loop.
+fn analyze_schedule() -> Result<(Vec<ModuleId>, Vec<ModuleId>), Vec<ModuleId>> {
+  let mut in_degree: HashMap<ModuleId, usize> = MODULES.iter().map(|&m| (m, 0)).collect();
+  for &(_, consumer) in INTRA_CYCLE_EDGES {
+    *in_degree.get_mut(&consumer).unwrap() += 1;
+  }
+  let downstreams: Vec<ModuleId> = MODULES
+    .iter()
+    .copied()
+    .filter(|m| in_degree[m] > 0)
+    .collect();
+
+  let mut remaining = in_degree.clone();
+  let mut ready: VecDeque<ModuleId> =
+    MODULES.iter().copied().filter(|m| remaining[m] == 0).collect();
+  let mut order = Vec::with_capacity(MODULES.len());
+  while let Some(module) = ready.pop_front() {
+    order.push(module);
+    for &(producer, consumer) in INTRA_CYCLE_EDGES {
+      if producer == module {
+        let deg = remaining.get_mut(&consumer).unwrap();
+        *deg -= 1;
+        if *deg == 0 {
+          ready.push_back(consumer);
+        }
+      }
+    }
+  }
+
+  if order.len() != MODULES.len() {
+    let cycle: Vec<ModuleId> = MODULES
+      .iter()
+      .copied()
+      .filter(|m| !order.contains(m))
+      .collect();
+    return Err(cycle);
+  }
+  Ok((order, downstreams))
 }
 
 impl Simulator {
   pub fn new() -> Self {
+    let (dispatch_order, downstreams) = analyze_schedule()
+      .unwrap_or_else(|cycle| panic!("combinational loop detected among modules: {:?}", cycle));
     Simulator {
       stamp: 0,
       request_stamp_map_table: HashMap::new(),
+      event_queue: BinaryHeap::new(),
+      debugger: Debugger::new(),
+      dispatch_order,
+      downstreams,
       cnt: Array::new_with_ports(1, 1),
       SimpleCounterInstance_triggered: false,
-      SimpleCounterInstance_event: VecDeque::new(),
+      clock: DEFAULT_CLOCK,
+      module_clocks: HashMap::new(),
+      element_clocks: HashMap::new(),
     }
   }
 
-  fn event_valid(&self, event: &VecDeque<usize>) -> bool {
-    event.front().map_or(false, |x| *x <= self.stamp)
+  /// Tie-break rank within a single stamp: modules follow the topological
+  /// dispatch order, and register ticks always come last.
+  fn event_rank(&self, event: Event) -> usize {
+    match event {
+      Event::Module(module) => self
+        .dispatch_order
+        .iter()
+        .position(|&m| m == module)
+        .unwrap_or(usize::MAX - 1),
+      Event::TickRegisters(_) => usize::MAX,
+    }
+  }
+
+  /// Insert `event` into the global event queue to fire at `stamp`.
+  pub fn schedule(&mut self, stamp: usize, event: Event) {
+    let rank = self.event_rank(event);
+    self.event_queue.push(Reverse((stamp, rank, event)));
+  }
+
+  /// The clock domain a module runs on (its override, else the default).
+  pub fn clock_of(&self, module: ModuleId) -> ClockDomain {
+    self.module_clocks.get(&module).copied().unwrap_or(self.clock)
+  }
+
+  /// The clock domain a named array or FIFO commits on (its override, else the
+  /// default). A FIFO's pushes and pops commit on its own domain's edges, which
+  /// are the consuming module's edges only when both share a domain.
+  pub fn element_clock(&self, name: &'static str) -> ClockDomain {
+    self.element_clocks.get(name).copied().unwrap_or(self.clock)
+  }
+
+  /// The distinct clock domains in play, so the scheduler can step each one's
+  /// edges independently (the general multi-domain case reduces to a single
+  /// entry here when every element shares the default clock).
+  fn distinct_domains(&self) -> Vec<ClockDomain> {
+    let mut domains = vec![self.clock];
+    for d in self
+      .module_clocks
+      .values()
+      .chain(self.element_clocks.values())
+    {
+      if !domains.contains(d) {
+        domains.push(*d);
+      }
+    }
+    domains
   }
 
   pub fn reset_downstream(&mut self) {
     self.SimpleCounterInstance_triggered = false;
   }
 
-  pub fn tick_registers(&mut self) {
-    self.cnt.tick(self.stamp);
+  /// Commit every array/FIFO whose assigned clock domain is `domain` — the
+  /// elements belonging to any other domain sit untouched until their own
+  /// edge fires this again with their domain.
+  pub fn tick_registers(&mut self, domain: ClockDomain) {
+    if self.element_clock("cnt") == domain {
+      self.cnt.tick(self.stamp);
+    }
   }
 
   pub fn reset_dram(&mut self) {}
 
-  fn simulate_SimpleCounterInstance(&mut self) {
-    if self.event_valid(&self.SimpleCounterInstance_event) {
-      let succ = modules::SimpleCounterInstance::SimpleCounterInstance(self);
-      if succ {
-        self.SimpleCounterInstance_event.pop_front();
-      } else {
-      }
-      self.SimpleCounterInstance_triggered = succ;
-    } // close event condition
-  } // close function
-}
+  /// Dump the current stamp/cycle, every `Array` payload, and the contents of
+  /// every `FIFO` — the state view shown whenever the debugger halts.
+  fn dump_state(&self) {
+    println!("-- debugger stop @ stamp {} (cycle {})", self.stamp, self.clock.cyclize(self.stamp));
+    println!("   cnt = {:?}", self.cnt.payload);
+  }
 
-pub fn simulate() {
-  let mut sim = Simulator::new();
-  let simulators: Vec<fn(&mut Simulator)> = vec![Simulator::simulate_SimpleCounterInstance];
-  let downstreams: Vec<fn(&mut Simulator)> = vec![];
+  /// Breakpoint / trace hook, invoked just before `module` is dispatched.
+  fn debug_on_module(&mut self, module: ModuleId) {
+    if !self.debugger.enabled {
+      return;
+    }
+    if self.debugger.trace_only {
+      println!("[trace] stamp {} dispatch {:?}", self.stamp, module);
+      return;
+    }
+    let hit = self.debugger.step || self.debugger.breakpoints.contains(&module);
+    if !hit {
+      return;
+    }
+    if self.debugger.repeat > 0 {
+      self.debugger.repeat -= 1;
+      return;
+    }
+    println!("[break] module {:?} about to dispatch", module);
+    self.dump_state();
+    self.debugger.prompt();
+  }
 
-  let mut idle_count = 0;
-  for i in 1..=100 {
-    sim.stamp = i * 100;
-    sim.reset_downstream();
+  /// Capture array values and FIFO occupancies so watchpoint changes can be
+  /// detected across a stamp.
+  fn watch_snapshot(&self) -> WatchSnapshot {
+    WatchSnapshot {
+      arrays: vec![("cnt", ValueCastTo::<i64>::cast(&self.cnt.payload[0]))],
+      fifos: vec![],
+    }
+  }
 
-    for simulate in simulators.iter() {
-      simulate(&mut sim);
+  /// Watchpoint / trace hook, invoked after a stamp's events have fired. Arrays
+  /// and FIFOs whose state changed are matched against the registered watches.
+  fn debug_on_watch(&mut self, before: &WatchSnapshot, after: &WatchSnapshot) {
+    if !self.debugger.enabled {
+      return;
     }
+    let mut hit = false;
+    for watch in &self.debugger.array_watch {
+      if let (Some(b), Some(a)) = (
+        before.arrays.iter().find(|(n, _)| *n == watch.name),
+        after.arrays.iter().find(|(n, _)| *n == watch.name),
+      ) {
+        if b.1 != a.1 && watch.predicate.as_ref().map_or(true, |p| p(a.1)) {
+          if self.debugger.trace_only {
+            println!("[trace] array {} = {}", watch.name, a.1);
+          } else {
+            println!("[watch] array {} -> {}", watch.name, a.1);
+            hit = true;
+          }
+        }
+      }
+    }
+    for watch in &self.debugger.fifo_watch {
+      if let (Some(b), Some(a)) = (
+        before.fifos.iter().find(|(n, _)| *n == watch.name),
+        after.fifos.iter().find(|(n, _)| *n == watch.name),
+      ) {
+        if b.1 != a.1 {
+          if self.debugger.trace_only {
+            println!("[trace] fifo {} len {} -> {}", watch.name, b.1, a.1);
+          } else {
+            println!("[watch] fifo {} len {} -> {}", watch.name, b.1, a.1);
+            hit = true;
+          }
+        }
+      }
+    }
+    if hit && !self.debugger.trace_only {
+      if self.debugger.repeat > 0 {
+        self.debugger.repeat -= 1;
+        return;
+      }
+      self.dump_state();
+      self.debugger.prompt();
+    }
+  }
 
-    for simulate in downstreams.iter() {
-      simulate(&mut sim);
+  fn set_triggered(&mut self, module: ModuleId, value: bool) {
+    match module {
+      ModuleId::SimpleCounterInstance => self.SimpleCounterInstance_triggered = value,
     }
+  }
 
-    let any_module_triggered = sim.SimpleCounterInstance_triggered;
+  /// Run a single scheduled event. Module activations that cannot make progress
+  /// (back-pressure, e.g. an empty input FIFO) are re-queued on the next cycle
+  /// edge rather than spinning the whole time wheel.
+  // `SimpleCounterInstance` has no FIFO pops (or any other `Array`/`FIFO`
+  // hazard), so it can never raise the underflow/deadlock faults that
+  // `SimFault` carries: it still returns a bare `bool` here and, when that is
+  // `false`, is simply retried on the next cycle edge rather than going through
+  // the `SimFault`/`handle_fault` path used by counter_system and
+  // processor_system.
+  fn dispatch(&mut self, event: Event) {
+    match event {
+      Event::TickRegisters(domain) => {
+        self.tick_registers(domain);
+      }
+      Event::Module(module) => {
+        self.debug_on_module(module);
+        let succ = match module {
+          ModuleId::SimpleCounterInstance => {
+            modules::SimpleCounterInstance::SimpleCounterInstance(self)
+          }
+        };
+        self.set_triggered(module, succ);
+        if !succ {
+          let retry = self.clock_of(module).next_cycle_stamp(self.stamp);
+          self.schedule(retry, Event::Module(module));
+        }
+      }
+    }
+  }
 
-    // Handle idle threshold
-    if !any_module_triggered {
-      idle_count += 1;
-      if idle_count >= 100 {
-        println!("Simulation stopped due to reaching idle threshold of 100");
-        break;
+  /// Event-driven core. Pops the earliest event, advances `stamp` to it, and
+  /// dispatches every event sharing that stamp before moving on, so idle time
+  /// is skipped entirely. `on_stamp` is invoked once per populated stamp after
+  /// its events have fired; returning `false` stops the run early.
+  pub fn run(&mut self, horizon: Option<usize>, mut on_stamp: impl FnMut(&Simulator) -> bool) {
+    self.reset_dram();
+    let limit = horizon.unwrap_or(DEFAULT_HORIZON);
+    // Every module not in DRIVEN_MODULES is a dispatch root: it has nothing
+    // upstream of it, so it fires on each rising edge of its own domain
+    // instead of waiting to be scheduled by another module.
+    for &root in MODULES.iter().filter(|m| !DRIVEN_MODULES.contains(m)) {
+      let domain = self.clock_of(root);
+      let mut edge = domain.period;
+      while edge <= limit {
+        self.schedule(edge, Event::Module(root));
+        edge += domain.period;
+      }
+    }
+    // Registers commit at every domain's own write-phase edge, and
+    // `tick_registers` only commits the elements assigned to the domain that
+    // fired: each distinct domain is stepped independently at its own period
+    // rather than on a shared, merged schedule.
+    for domain in self.distinct_domains() {
+      let mut edge = domain.period + domain.write_phase;
+      while edge <= limit {
+        self.schedule(edge, Event::TickRegisters(domain));
+        edge += domain.period;
       }
-    } else {
-      idle_count = 0;
     }
 
-    sim.stamp += 50;
-    sim.tick_registers();
-    sim.reset_dram();
-    unsafe {
-      // Tick all DRAM memory interfaces
+    while let Some(Reverse((stamp, _, _))) = self.event_queue.peek().copied() {
+      if horizon.map_or(false, |h| stamp > h) {
+        break;
+      }
+      self.stamp = stamp;
+      self.reset_downstream();
+      let before = self.watch_snapshot();
+      while matches!(self.event_queue.peek(), Some(Reverse((s, _, _))) if *s == stamp) {
+        let Reverse((_, _, event)) = self.event_queue.pop().unwrap();
+        self.dispatch(event);
+      }
+      let after = self.watch_snapshot();
+      self.debug_on_watch(&before, &after);
+      if !on_stamp(self) {
+        break;
+      }
     }
   }
 }
+
+pub fn simulate() {
+  let mut sim = Simulator::new();
+  sim.run(Some(DEFAULT_HORIZON), |_| true);
+}